@@ -1,7 +1,9 @@
 use core::f64::consts::PI;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
 use std::fs;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone)]
 struct FiringPlan {
@@ -63,6 +65,14 @@ impl Metres {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+struct Kilograms(f64);
+
+// Horizontal wind speed, metres/second; positive blows in the direction of
+// travel. Unlike `MetresPerSecond` this may be negative (a headwind).
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+struct Wind(f64);
+
 #[derive(Debug)]
 struct Coordinates {
     x: Metres,
@@ -90,42 +100,145 @@ impl Trajectory {
 }
 
 // We fire from (0, 0) and the wall is at (+/-distance, 0) up to (+/-distance, height)
-fn simulate(p: &FiringPlan, params: &Params) -> Trajectory {
+//
+// With no drag (`drag_coefficient` zero) this integrates the same parabola the
+// closed-form `y = vt·sinθ − ½gt²` describes; with drag it numerically
+// integrates quadratic air resistance instead, so the wall-clearing check is
+// made against the integrated path rather than an analytic time-at-wall.
+// `wind` is the horizontal air speed the drag term is computed relative to.
+fn simulate(p: &FiringPlan, params: &Params, wind: &Wind) -> Trajectory {
     const G: f64 = 9.81; // gravity on Earth
-    let cos_theta = p.angle.cos();
-    let sin_theta = p.angle.sin();
-
-    // Calculate co-ordinates of cannon-ball at time t:
-    let position = |t: &Seconds| {
-        let vt = p.velocity.0 * t.0;
-        let x = Metres(vt * cos_theta);
-        let y = Metres(vt * sin_theta - (0.5 * G * t.0 * t.0));
-        Coordinates { x, y }
-    };
+    let dt = params.simulation_step_size.0;
+    let k_over_m = params.drag_coefficient / params.mass.0;
 
-    // What's the cannon-ball height at the point of the wall?
-    // i.e., did we clear the wall?
-    let t_at_wall = Seconds(params.wall_distance.0 / (p.velocity.0 * cos_theta));
-    let coords_at_wall = position(&t_at_wall);
-    let did_hit_wall = coords_at_wall.y.is_positive() && coords_at_wall.y < params.wall_height;
+    let mut x = 0.0_f64;
+    let mut y = 0.0_f64;
+    let mut vx = p.velocity.0 * p.angle.cos();
+    let mut vy = p.velocity.0 * p.angle.sin();
 
-    // Build up cannon-ball trajectory:
     let mut path = Vec::new();
-    let mut t = Seconds(0.0);
-    let mut y = Metres(0.0);
-    while t.0 == 0.0 || (did_hit_wall && t < t_at_wall) || (!did_hit_wall && y.is_positive()) {
-        t = Seconds(t.0 + params.simulation_step_size.0);
-        let coords = position(&t);
-        y = coords.y.clone();
+    let mut past_wall = false;
+    let mut stopped_at_wall = false;
+
+    loop {
+        // Quadratic drag opposes velocity relative to the wind, scaled by speed:
+        let vx_rel = vx - wind.0;
+        let speed = (vx_rel * vx_rel + vy * vy).sqrt();
+        let ax = -k_over_m * speed * vx_rel;
+        let ay = -G - k_over_m * speed * vy;
+
+        // Semi-implicit Euler: update velocity, then advance position with it.
+        vx += ax * dt;
+        vy += ay * dt;
+        x += vx * dt;
+        y += vy * dt;
+
+        let coords = Coordinates {
+            x: Metres(x),
+            y: Metres(y),
+        };
+
+        // What's the cannon-ball height at the point of the wall?
+        // i.e., did we clear the wall?
+        if !past_wall && coords.x >= params.wall_distance {
+            past_wall = true;
+            stopped_at_wall = coords.y.is_positive() && coords.y < params.wall_height;
+        }
+
+        let landed = !coords.y.is_positive();
         path.push(coords);
+
+        if stopped_at_wall || landed {
+            break;
+        }
     }
     Trajectory(path)
 }
 
-// We maximize how far the cannon ball has travelled horizontally.
-fn evaluate(p: &FiringPlan, params: &Params) -> Fitness {
-    let traj = simulate(&p, &params);
-    Fitness(traj.distance().0)
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ObjectiveMode {
+    // Maximize how far the cannon ball has travelled horizontally.
+    Distance,
+    // Land the cannon ball inside a rectangular target zone.
+    TargetZone,
+}
+
+// A flat bonus big enough to dominate any distance-based tie-breaker,
+// so a hit always outranks a near miss.
+const TARGET_HIT_BONUS: f64 = 1e6;
+
+// Liang-Barsky segment/rectangle intersection test: does the segment from
+// `p0` to `p1` pass through the target zone?
+fn segment_hits_target(p0: &Coordinates, p1: &Coordinates, params: &Params) -> bool {
+    let (x0, y0) = (p0.x.0, p0.y.0);
+    let dx = p1.x.0 - x0;
+    let dy = p1.y.0 - y0;
+
+    let mut t_min = 0.0_f64;
+    let mut t_max = 1.0_f64;
+
+    let edges = [
+        (-dx, x0 - params.target_x0.0),
+        (dx, params.target_x1.0 - x0),
+        (-dy, y0 - params.target_y0.0),
+        (dy, params.target_y1.0 - y0),
+    ];
+
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t_min = t_min.max(r);
+            } else {
+                t_max = t_max.min(r);
+            }
+        }
+    }
+
+    t_min <= t_max
+}
+
+// Fitness that rewards landing in the target zone: a flat bonus plus the
+// highest apex reached (to separate plans that both clear it), otherwise a
+// score that decreases with the closest approach to the target centre.
+fn evaluate_target_zone(traj: &Trajectory, params: &Params) -> Fitness {
+    let centre_x = (params.target_x0.0 + params.target_x1.0) / 2.0;
+    let centre_y = (params.target_y0.0 + params.target_y1.0) / 2.0;
+
+    let mut hit = false;
+    let mut apex = Metres(f64::MIN);
+    let mut min_dist = f64::MAX;
+
+    for pair in traj.0.windows(2) {
+        if segment_hits_target(&pair[0], &pair[1], params) {
+            hit = true;
+        }
+        for c in pair {
+            if c.y > apex {
+                apex = c.y.clone();
+            }
+            let dist = ((c.x.0 - centre_x).powi(2) + (c.y.0 - centre_y).powi(2)).sqrt();
+            min_dist = min_dist.min(dist);
+        }
+    }
+
+    if hit {
+        Fitness(TARGET_HIT_BONUS + apex.0)
+    } else {
+        Fitness(-min_dist)
+    }
+}
+
+fn evaluate(p: &FiringPlan, params: &Params, wind: &Wind) -> Fitness {
+    let traj = simulate(p, params, wind);
+    match params.objective {
+        ObjectiveMode::Distance => Fitness(traj.distance().0),
+        ObjectiveMode::TargetZone => evaluate_target_zone(&traj, params),
+    }
 }
 
 #[derive(Debug)]
@@ -134,47 +247,84 @@ struct Individual {
     fitness: Fitness,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OptimizerMode {
+    // Genetic algorithm: keep the `elite_count` fittest plans and breed the
+    // rest of each generation by tournament selection, crossover and mutation.
+    Evolutionary,
+    // Single-solution search that can accept worse neighbours early on
+    // (while temperature is high) to escape local optima.
+    SimulatedAnnealing,
+}
+
 struct Params {
     wall_height: Metres,
     wall_distance: Metres,
     simulation_step_size: Seconds,
     seed: u64,
     pop_size: usize,
-    num_evaluations: usize,
+    max_time: Duration,
+    optimizer: OptimizerMode,
+    start_temp: f64,
+    end_temp: f64,
+    tournament_size: usize,
+    elite_count: usize,
+    crossover_rate: f64,
+    objective: ObjectiveMode,
+    target_x0: Metres,
+    target_x1: Metres,
+    target_y0: Metres,
+    target_y1: Metres,
+    drag_coefficient: f64,
+    mass: Kilograms,
+    true_wind: Wind,
 }
 
-fn main() {
-    let params = Params {
-        wall_height: Metres(25.0),
-        wall_distance: Metres(10.0),
-        simulation_step_size: Seconds(0.01),
-        seed: 1,
-        pop_size: 25,
-        num_evaluations: 3000,
-    };
+fn mutate<R: Rng>(plan: &FiringPlan, rng: &mut R) -> FiringPlan {
+    FiringPlan::new(
+        MetresPerSecond((plan.velocity.0 + rng.gen::<f64>() - 0.5).max(0.1)),
+        Radians((plan.angle.0 + rng.gen::<f64>() - 0.5).clamp(0.1, PI / 2.0)),
+    )
+}
 
-    let mut rng: StdRng = SeedableRng::seed_from_u64(params.seed);
+// Arithmetic blend of two parents: pick alpha in [0,1] and mix velocity and
+// angle in that proportion.
+fn crossover<R: Rng>(a: &FiringPlan, b: &FiringPlan, rng: &mut R) -> FiringPlan {
+    let alpha: f64 = rng.gen();
+    FiringPlan::new(
+        MetresPerSecond(a.velocity.0 * alpha + b.velocity.0 * (1.0 - alpha)),
+        Radians(a.angle.0 * alpha + b.angle.0 * (1.0 - alpha)),
+    )
+}
 
-    fn mutate<R: Rng>(plan: &FiringPlan, rng: &mut R) -> FiringPlan {
-        FiringPlan::new(
-            MetresPerSecond((plan.velocity.0 + rng.gen::<f64>() - 0.5).max(0.1)),
-            Radians(
-                (plan.angle.0 + rng.gen::<f64>() - 0.5)
-                    .max(0.1)
-                    .min(PI / 2.0),
-            ),
-        )
+// Sample `k` individuals uniformly and return the fittest.
+fn tournament_select<'a, R: Rng>(pop: &'a [Individual], k: usize, rng: &mut R) -> &'a FiringPlan {
+    let mut best = &pop[rng.gen_range(0..pop.len())];
+    for _ in 1..k {
+        let candidate = &pop[rng.gen_range(0..pop.len())];
+        if candidate.fitness > best.fitness {
+            best = candidate;
+        }
     }
+    &best.plan
+}
 
-    let mut best_fitness_to_date = Fitness(0.0);
+// Runs until `params.max_time` elapses, scoring each generation's population
+// in parallel, and returns the number of generations completed.
+fn run_evolutionary(params: &Params, wind: &Wind, rng: &mut StdRng) -> usize {
+    // Not all objectives are non-negative (e.g. TargetZone scores misses as
+    // `-distance`), so start from an unconditionally-beatable sentinel.
+    let mut best_fitness_to_date = Fitness(f64::NEG_INFINITY);
 
-    let mut ps = FiringPlan::randoms(&mut rng, params.pop_size);
+    let mut ps = FiringPlan::randoms(rng, params.pop_size);
 
-    for r in 0..params.num_evaluations {
+    let start = Instant::now();
+    let mut r = 0;
+    while start.elapsed() < params.max_time {
         let mut pop: Vec<Individual> = ps
-            .iter()
+            .par_iter()
             .map(|plan| {
-                let fitness = evaluate(&plan, &params);
+                let fitness = evaluate(plan, params, wind);
                 Individual {
                     plan: plan.clone(),
                     fitness,
@@ -188,17 +338,304 @@ fn main() {
             if best.fitness > best_fitness_to_date {
                 best_fitness_to_date = best.fitness.clone();
                 println!("Epoc {} Fitness {}", r, best.fitness.0);
-                let traj = simulate(&best.plan, &params);
+                let traj = simulate(&best.plan, params, wind);
                 traj.save(&format!("traj/{}.dat", r));
             }
         }
 
         for i in 0..params.pop_size {
-            ps[i] = if i <= 1 {
+            ps[i] = if i < params.elite_count {
                 pop[i].plan.clone()
             } else {
-                mutate(&(pop[i].plan), &mut rng)
+                let parent_a = tournament_select(&pop, params.tournament_size, rng);
+                if rng.gen::<f64>() < params.crossover_rate {
+                    let parent_b = tournament_select(&pop, params.tournament_size, rng);
+                    let child = crossover(parent_a, parent_b, rng);
+                    mutate(&child, rng)
+                } else {
+                    mutate(parent_a, rng)
+                }
             }
         }
+
+        r += 1;
     }
+    r
+}
+
+// Temperature schedule driven by search progress `t` in [0, 1]: a geometric
+// interpolation from `start_temp` down to `end_temp`.
+fn temperature(params: &Params, t: f64) -> f64 {
+    params.start_temp.powf(1.0 - t) * params.end_temp.powf(t)
+}
+
+// Runs until `params.max_time` elapses and returns the number of steps taken.
+fn run_simulated_annealing(params: &Params, wind: &Wind, rng: &mut StdRng) -> usize {
+    let mut current = FiringPlan::random(rng);
+    let mut current_fitness = evaluate(&current, params, wind);
+
+    let mut best = current.clone();
+    let mut best_fitness = current_fitness.clone();
+
+    println!("Epoc {} Fitness {}", 0, best_fitness.0);
+    simulate(&best, params, wind).save(&format!("traj/{}.dat", 0));
+
+    let start = Instant::now();
+    let mut r = 0;
+    while start.elapsed() < params.max_time {
+        let t = (start.elapsed().as_secs_f64() / params.max_time.as_secs_f64()).min(1.0);
+        let temp = temperature(params, t);
+
+        let candidate = mutate(&current, rng);
+        let candidate_fitness = evaluate(&candidate, params, wind);
+
+        let delta = candidate_fitness.0 - current_fitness.0;
+        let accept = delta > 0.0 || rng.gen::<f64>() < (delta / temp).exp();
+
+        if accept {
+            current = candidate;
+            current_fitness = candidate_fitness;
+        }
+
+        if current_fitness > best_fitness {
+            best = current.clone();
+            best_fitness = current_fitness.clone();
+            println!("Epoc {} Fitness {}", r, best_fitness.0);
+            simulate(&best, params, wind).save(&format!("traj/{}.dat", r));
+        }
+
+        r += 1;
+    }
+    r
+}
+
+// Particles used to infer the unknown wind from noisy range measurements.
+const NUM_PARTICLES: usize = 2000;
+// Standard deviation of the simulated range-measurement noise, metres.
+const MEASUREMENT_NOISE_STD: f64 = 1.0;
+// Standard deviation of the per-step random wind perturbation, m/s.
+const WIND_PERTURBATION_STD: f64 = 0.5;
+
+// A particle's hypothesis of the flying ball's state: where it is, how fast
+// it's moving, what wind would explain that, and how well it currently
+// matches the observed measurements.
+#[derive(Debug, Clone)]
+struct Particle {
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    wind: f64,
+    weight: f64,
+}
+
+impl Particle {
+    fn new_random<R: Rng>(plan: &FiringPlan, rng: &mut R) -> Particle {
+        Particle {
+            x: 0.0,
+            y: 0.0,
+            vx: plan.velocity.0 * plan.angle.cos(),
+            vy: plan.velocity.0 * plan.angle.sin(),
+            wind: rng.gen::<f64>() * 20.0 - 10.0, // uninformed prior over +/- 10 m/s
+            weight: 1.0 / NUM_PARTICLES as f64,
+        }
+    }
+
+    // Advance this particle's own ball state by one physics step, using its
+    // currently hypothesised wind.
+    fn step(&mut self, params: &Params) {
+        const G: f64 = 9.81;
+        let dt = params.simulation_step_size.0;
+        let k_over_m = params.drag_coefficient / params.mass.0;
+
+        let vx_rel = self.vx - self.wind;
+        let speed = (vx_rel * vx_rel + self.vy * self.vy).sqrt();
+        let ax = -k_over_m * speed * vx_rel;
+        let ay = -G - k_over_m * speed * self.vy;
+
+        self.vx += ax * dt;
+        self.vy += ay * dt;
+        self.x += self.vx * dt;
+        self.y += self.vy * dt;
+    }
+}
+
+// Box-Muller transform: turn two uniform samples into one standard-normal one.
+fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = rng.gen::<f64>().max(f64::EPSILON);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+// Draw `particles.len()` new particles with probability proportional to
+// weight, using systematic (low-variance) resampling.
+fn systematic_resample<R: Rng>(particles: &[Particle], rng: &mut R) -> Vec<Particle> {
+    let n = particles.len();
+    let mut cumulative = Vec::with_capacity(n);
+    let mut acc = 0.0;
+    for particle in particles {
+        acc += particle.weight;
+        cumulative.push(acc);
+    }
+
+    let start: f64 = rng.gen::<f64>() / n as f64;
+    let mut resampled = Vec::with_capacity(n);
+    let mut j = 0;
+    for i in 0..n {
+        let target = start + i as f64 / n as f64;
+        while j < n - 1 && cumulative[j] < target {
+            j += 1;
+        }
+        resampled.push(particles[j].clone());
+    }
+    resampled
+}
+
+// Runs one pass of the particle filter over the whole calibration flight.
+// Returns `None` on weight collapse, since at that point the particles are
+// partway through the flight and a fresh scatter at t=0 could never resync
+// with the remaining measurements — the caller should restart from t=0
+// instead of limping on out of sync.
+fn run_particle_filter<R: Rng>(
+    calibration_plan: &FiringPlan,
+    true_traj: &Trajectory,
+    params: &Params,
+    rng: &mut R,
+) -> Option<Vec<Particle>> {
+    let mut particles: Vec<Particle> = (0..NUM_PARTICLES)
+        .map(|_| Particle::new_random(calibration_plan, rng))
+        .collect();
+
+    for measurement in &true_traj.0 {
+        // Predict: perturb each particle's wind hypothesis and advance it to
+        // this measurement's timestep before comparing it to the observation.
+        for particle in &mut particles {
+            particle.wind += standard_normal(rng) * WIND_PERTURBATION_STD;
+            particle.step(params);
+        }
+
+        let noisy_x = measurement.x.0 + standard_normal(rng) * MEASUREMENT_NOISE_STD;
+
+        // Update: weight each particle by how well its predicted position
+        // explains the noisy measurement.
+        for particle in &mut particles {
+            let diff = noisy_x - particle.x;
+            particle.weight *= (-0.5 * (diff / MEASUREMENT_NOISE_STD).powi(2)).exp();
+        }
+
+        let total_weight: f64 = particles.iter().map(|particle| particle.weight).sum();
+        if total_weight < f64::EPSILON {
+            return None;
+        }
+        for particle in &mut particles {
+            particle.weight /= total_weight;
+        }
+
+        particles = systematic_resample(&particles, rng)
+            .into_iter()
+            .map(|mut particle| {
+                particle.weight = 1.0 / NUM_PARTICLES as f64;
+                particle
+            })
+            .collect();
+    }
+
+    Some(particles)
+}
+
+// Fly a calibration shot through the true wind and run the particle filter
+// over its noisy range measurements, starting over whenever the weights
+// collapse; the estimate is the mean wind of the surviving particles.
+fn estimate_wind<R: Rng>(params: &Params, rng: &mut R) -> Wind {
+    let calibration_plan = FiringPlan::new(MetresPerSecond(20.0), Radians(PI / 4.0));
+    let true_traj = simulate(&calibration_plan, params, &params.true_wind);
+
+    let particles = loop {
+        if let Some(particles) = run_particle_filter(&calibration_plan, &true_traj, params, rng) {
+            break particles;
+        }
+    };
+
+    Wind(particles.iter().map(|particle| particle.wind).sum::<f64>() / NUM_PARTICLES as f64)
+}
+
+// Select the optimizer via `--optimizer <evolutionary|simulated-annealing>`
+// (or the `ga`/`sa` shorthands); defaults to the evolutionary search.
+fn parse_optimizer_mode() -> OptimizerMode {
+    let args: Vec<String> = std::env::args().collect();
+    let value = args
+        .iter()
+        .position(|arg| arg == "--optimizer")
+        .and_then(|i| args.get(i + 1));
+
+    match value.map(String::as_str) {
+        Some("simulated-annealing") | Some("sa") => OptimizerMode::SimulatedAnnealing,
+        Some("evolutionary") | Some("ga") | None => OptimizerMode::Evolutionary,
+        Some(other) => panic!("unknown --optimizer value: {other}"),
+    }
+}
+
+// Select the fitness objective via `--objective <distance|target-zone>`
+// (or the `target` shorthand); defaults to maximizing distance.
+fn parse_objective_mode() -> ObjectiveMode {
+    let args: Vec<String> = std::env::args().collect();
+    let value = args
+        .iter()
+        .position(|arg| arg == "--objective")
+        .and_then(|i| args.get(i + 1));
+
+    match value.map(String::as_str) {
+        Some("target-zone") | Some("target") => ObjectiveMode::TargetZone,
+        Some("distance") | None => ObjectiveMode::Distance,
+        Some(other) => panic!("unknown --objective value: {other}"),
+    }
+}
+
+fn main() {
+    let params = Params {
+        wall_height: Metres(25.0),
+        wall_distance: Metres(10.0),
+        simulation_step_size: Seconds(0.01),
+        seed: 1,
+        pop_size: 25,
+        max_time: Duration::from_secs(30),
+        optimizer: parse_optimizer_mode(),
+        start_temp: 1e6,
+        end_temp: 1e2,
+        tournament_size: 3,
+        elite_count: 2,
+        crossover_rate: 0.7,
+        objective: parse_objective_mode(),
+        target_x0: Metres(18.0),
+        target_x1: Metres(22.0),
+        target_y0: Metres(0.0),
+        target_y1: Metres(4.0),
+        // Must be non-zero: quadratic drag is the only way wind enters the
+        // physics (`vx_rel = vx - wind` only appears inside the drag term),
+        // so a zero coefficient makes the ball's horizontal motion carry no
+        // information about wind at all and the particle filter just fits
+        // measurement noise.
+        drag_coefficient: 0.05,
+        mass: Kilograms(1.0),
+        true_wind: Wind(3.0),
+    };
+
+    let mut rng: StdRng = SeedableRng::seed_from_u64(params.seed);
+
+    let estimated_wind = estimate_wind(&params, &mut rng);
+    println!(
+        "Estimated wind {:.2} m/s (true wind {:.2} m/s)",
+        estimated_wind.0, params.true_wind.0
+    );
+
+    let generations = match params.optimizer {
+        OptimizerMode::Evolutionary => run_evolutionary(&params, &estimated_wind, &mut rng),
+        OptimizerMode::SimulatedAnnealing => {
+            run_simulated_annealing(&params, &estimated_wind, &mut rng)
+        }
+    };
+    println!(
+        "Completed {} generations in {:?}",
+        generations, params.max_time
+    );
 }